@@ -0,0 +1,250 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Exports a fully rendered, static copy of a site for hosting without a
+//! running server.
+//!
+//! `export` walks the same topic/post structure that `routes` serves live,
+//! renders every post and every loose `index.md` landing page (the site
+//! root's and each topic's) through the supplied `render` function, and
+//! copies each topic's static assets alongside the rendered HTML.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, trace};
+
+use super::common;
+use super::config::AppConfig;
+use super::{Context, Result};
+
+/// Output directory used when `build --out` is not given.
+pub(crate) const DEFAULT_OUT_DIR: &str = "site/public";
+
+/// Renders the posts and landing pages of the `static`, `main` and
+/// `site.topics` directories under `app`'s webroot through `render` and
+/// copies those topics' static assets into `out_dir`, overwriting any stale
+/// files already there. Webroot directories not named by `site.topics` are
+/// not exported.
+///
+/// `render` is taken as a function rather than a concrete `render::Engine`
+/// so this logic can be exercised with a stub in tests.
+pub(crate) fn export<P, F>(app: &AppConfig, render: F, out_dir: P) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> Result<String>,
+{
+    let out_dir = out_dir.as_ref();
+    info!("Building static site into {}", out_dir.display());
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create output directory '{}'", out_dir.display()))?;
+
+    let webroot = &app.docpaths.webroot;
+    render_index(&render, Path::new(webroot), out_dir)?;
+
+    export_topic(&render, "static", webroot, out_dir)?;
+    export_topic(&render, "main", webroot, out_dir)?;
+
+    for topic in &app.site.topics {
+        let slug = common::slugify(topic);
+        export_topic(&render, &slug, webroot, out_dir)?;
+    }
+
+    Ok(())
+}
+
+fn export_topic<F>(render: &F, topic: &str, webroot: &str, out_dir: &Path) -> Result<()>
+where
+    F: Fn(&Path) -> Result<String>,
+{
+    debug!("Exporting topic '{}'", topic);
+
+    let topic_dir = Path::new(webroot).join(topic);
+    let topic_out_dir = out_dir.join(topic);
+    render_index(render, &topic_dir, &topic_out_dir)?;
+
+    let ext_pattern = format!("{}/{}/ext/*", webroot, topic);
+    if let Ok(assets) = common::path_matches(&ext_pattern) {
+        copy_assets(&assets, &topic_out_dir.join("ext"))?;
+    }
+
+    let posts_pattern = format!("{}/{}/posts/*", webroot, topic);
+    if let Ok(posts) = common::path_matches(&posts_pattern) {
+        for post in &posts {
+            render_post(render, post, &topic_out_dir.join("posts"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn copy_assets(assets: &[PathBuf], dest_dir: &Path) -> Result<()> {
+    if assets.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dest_dir)
+        .with_context(|| format!("failed to create '{}'", dest_dir.display()))?;
+
+    for asset in assets {
+        let Some(name) = asset.file_name() else { continue };
+        let dest = dest_dir.join(name);
+        trace!("Copying asset '{}' -> '{}'", asset.display(), dest.display());
+        fs::copy(asset, &dest)
+            .with_context(|| format!("failed to copy '{}' to '{}'", asset.display(), dest.display()))?;
+    }
+
+    Ok(())
+}
+
+fn render_post<F>(render: &F, src: &Path, dest_dir: &Path) -> Result<()>
+where
+    F: Fn(&Path) -> Result<String>,
+{
+    let stem = src.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "index".to_owned());
+    render_to(render, src, &dest_dir.join(format!("{}.html", stem)))
+}
+
+/// Renders `src_dir`'s loose `index.md` landing page, if one exists, into
+/// `dest_dir/index.html`. Used for both the site root and every topic
+/// directory, neither of which is covered by the `posts`/`ext` globs.
+fn render_index<F>(render: &F, src_dir: &Path, dest_dir: &Path) -> Result<()>
+where
+    F: Fn(&Path) -> Result<String>,
+{
+    let index = src_dir.join("index.md");
+    if !index.exists() {
+        return Ok(());
+    }
+
+    render_to(render, &index, &dest_dir.join("index.html"))
+}
+
+fn render_to<F>(render: &F, src: &Path, dest: &Path) -> Result<()>
+where
+    F: Fn(&Path) -> Result<String>,
+{
+    trace!("Rendering '{}'", src.display());
+    let html = render(src)
+        .with_context(|| format!("failed to render '{}'", src.display()))?;
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create '{}'", parent.display()))?;
+    }
+
+    trace!("Writing rendered page to '{}'", dest.display());
+    fs::write(dest, html)
+        .with_context(|| format!("failed writing '{}'", dest.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DocPaths, Server, Site};
+
+    fn test_config(webroot: &Path, topics: Vec<&str>) -> AppConfig {
+        AppConfig {
+            site: Site {
+                name: "Test Site".to_owned(),
+                author: "Test Author".to_owned(),
+                template: "default.tmpl".to_owned(),
+                topics: topics.into_iter().map(str::to_owned).collect(),
+            },
+            server: Server::new(),
+            docpaths: DocPaths {
+                templates: webroot.join("templates").display().to_string(),
+                webroot: webroot.join("webroot").display().to_string(),
+            },
+        }
+    }
+
+    fn stub_render(path: &Path) -> Result<String> {
+        Ok(format!("<html>{}</html>", path.display()))
+    }
+
+    #[test]
+    fn copy_assets_overwrites_a_stale_destination_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let asset = dir.path().join("style.css");
+        fs::write(&asset, "fresh").unwrap();
+
+        let dest_dir = dir.path().join("out");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::write(dest_dir.join("style.css"), "stale").unwrap();
+
+        copy_assets(&[asset], &dest_dir).unwrap();
+
+        assert_eq!(fs::read_to_string(dest_dir.join("style.css")).unwrap(), "fresh");
+    }
+
+    #[test]
+    fn export_mirrors_static_main_and_topic_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path(), vec!["One"]);
+
+        let webroot = Path::new(&config.docpaths.webroot);
+        fs::create_dir_all(webroot.join("static/ext")).unwrap();
+        fs::write(webroot.join("static/ext/style.css"), "css").unwrap();
+
+        fs::create_dir_all(webroot.join("main/posts")).unwrap();
+        fs::write(webroot.join("main/posts/hello.md"), "# Hello").unwrap();
+
+        fs::create_dir_all(webroot.join("one/posts")).unwrap();
+        fs::write(webroot.join("one/posts/first.md"), "# First").unwrap();
+
+        let out_dir = dir.path().join("public");
+        export(&config, stub_render, &out_dir).unwrap();
+
+        assert_eq!(fs::read_to_string(out_dir.join("static/ext/style.css")).unwrap(), "css");
+        assert!(out_dir.join("main/posts/hello.html").exists());
+        assert!(out_dir.join("one/posts/first.html").exists());
+    }
+
+    #[test]
+    fn export_renders_site_and_topic_landing_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path(), vec!["One"]);
+
+        let webroot = Path::new(&config.docpaths.webroot);
+        fs::create_dir_all(webroot.join("one/posts")).unwrap();
+        fs::write(webroot.join("index.md"), "# Home").unwrap();
+        fs::write(webroot.join("one/index.md"), "# One").unwrap();
+
+        let out_dir = dir.path().join("public");
+        export(&config, stub_render, &out_dir).unwrap();
+
+        assert!(out_dir.join("index.html").exists());
+        assert!(out_dir.join("one/index.html").exists());
+    }
+
+    #[test]
+    fn export_overwrites_a_stale_rendered_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path(), vec![]);
+
+        let webroot = Path::new(&config.docpaths.webroot);
+        fs::create_dir_all(webroot.join("main/posts")).unwrap();
+        fs::write(webroot.join("main/posts/hello.md"), "# Hello").unwrap();
+
+        let out_dir = dir.path().join("public");
+        fs::create_dir_all(out_dir.join("main/posts")).unwrap();
+        fs::write(out_dir.join("main/posts/hello.html"), "stale").unwrap();
+
+        export(&config, stub_render, &out_dir).unwrap();
+
+        let rendered = fs::read_to_string(out_dir.join("main/posts/hello.html")).unwrap();
+        assert_ne!(rendered, "stale");
+    }
+}
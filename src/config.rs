@@ -8,9 +8,10 @@ http://opensource.org/licenses/MIT>, at your option. This file may not be
 copied, modified, or distributed except according to those terms.
 */
 
+use std::collections::HashSet;
 use std::fs::create_dir_all;
 use std::{io::BufRead, usize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand, crate_authors, crate_description, crate_version};
 use log::{debug, error, info, trace};
@@ -20,6 +21,9 @@ use serde::{Serialize, Deserialize};
 use super::common;
 use super::{anyhow, Context, Result};
 
+/// Prefix identifying environment variables that override `AppConfig` fields.
+const ENV_PREFIX: &str = "ARSE__";
+
 fn args() -> App<'static, 'static> {
     App::new("A Rust Site Engine")
 	.version(crate_version!())
@@ -33,13 +37,35 @@ fn args() -> App<'static, 'static> {
         .subcommand(SubCommand::with_name("run")
 		    .about("Run the site server")
 		    .arg(Arg::with_name("config")
-			 .help("Provides the path to the server configuration file.")
-			 .required(true)
+			 .help("Provides the path to the server configuration file. If omitted, searches for `config.toml` in the current directory and its parents.")
+			 .required(false)
 			 .takes_value(true)
 			 .index(1)))
 	.subcommand(SubCommand::with_name("new")
 		    .about("Generates a base directory structure and configuration file for a new site")
-		    )
+		    .arg(Arg::with_name("scaffold")
+			 .long("scaffold")
+			 .help("Path to a scaffold bundle to expand instead of the embedded default.")
+			 .takes_value(true)))
+	.subcommand(SubCommand::with_name("build")
+		    .about("Renders the site to static HTML in an output directory")
+		    .arg(Arg::with_name("config")
+			 .help("Provides the path to the server configuration file. If omitted, searches for `config.toml` in the current directory and its parents.")
+			 .required(false)
+			 .takes_value(true)
+			 .index(1))
+		    .arg(Arg::with_name("out")
+			 .short("o")
+			 .long("out")
+			 .help("Directory to write the static site into. Default: site/public")
+			 .takes_value(true)))
+	.subcommand(SubCommand::with_name("check")
+		    .about("Validates the configuration and site tree without starting the server")
+		    .arg(Arg::with_name("config")
+			 .help("Provides the path to the server configuration file. If omitted, searches for `config.toml` in the current directory and its parents.")
+			 .required(false)
+			 .takes_value(true)
+			 .index(1)))
 }
 
 /// TODO Document this public function
@@ -71,7 +97,27 @@ pub(crate) fn load() -> Result<AppConfig> {
 	let reader = std::io::stdin();
 	let mut reader = reader.lock();
 	let current_path = std::env::current_dir().context("failed to get current working directory")?;
-	let _ = AppConfig::generate(current_path, &mut reader);
+	let scaffold = matches.subcommand_matches("new")
+	    .and_then(|new| new.value_of("scaffold"));
+	let _ = AppConfig::generate(current_path, &mut reader, scaffold);
+	std::process::exit(0);
+    } else if matches.is_present("build") {
+	trace!("Application called with `build` subcommand - exporting a static copy of the site");
+	let build = matches.subcommand_matches("build").unwrap();
+	let out_dir = build.value_of("out").unwrap_or(super::build::DEFAULT_OUT_DIR);
+	let built_config = config_from_positional(build.value_of("config"))?;
+	let engine = super::render::Engine::new(built_config);
+	super::build::export(&engine.app, |path| engine.render(path), out_dir)?;
+	std::process::exit(0);
+    } else if matches.is_present("check") {
+	trace!("Application called with `check` subcommand - validating configuration and site tree");
+	let check = matches.subcommand_matches("check").unwrap();
+	let checked_config = config_from_positional(check.value_of("config"))?;
+	if let Err(err) = checked_config.check() {
+	    error!("{}", err);
+	    std::process::exit(1);
+	}
+	info!("Site configuration and tree are valid");
 	std::process::exit(0);
     } else {
 	let msg = "Unable to load configuration".to_owned();
@@ -84,9 +130,7 @@ pub(crate) fn load() -> Result<AppConfig> {
 
 fn runner_config(m: ArgMatches) -> Result<AppConfig> {
     if let Some(run) = m.subcommand_matches("run") {
-	let value = run.value_of("config").unwrap();
-	let config = AppConfig::from_path(value)?;
-	Ok(config)
+	config_from_positional(run.value_of("config"))
     } else {
 	let msg = "Failed to read arguments for 'run' subcommand".to_owned();
 	error!("{}", &msg);
@@ -94,6 +138,45 @@ fn runner_config(m: ArgMatches) -> Result<AppConfig> {
     }
 }
 
+/// Loads an `AppConfig` from an explicit path, falling back to discovery of
+/// `config.toml` from the current directory when no path is given.
+fn config_from_positional(value: Option<&str>) -> Result<AppConfig> {
+    match value {
+	Some(value) => AppConfig::from_path(value),
+	None => {
+	    trace!("No config path given - discovering config.toml from the current directory");
+	    let current_dir = std::env::current_dir().context("failed to get current working directory")?;
+	    let discovered = discover_config(&current_dir)?;
+	    AppConfig::from_path(discovered)
+	}
+    }
+}
+
+/// Searches for `config.toml` starting at `dir` and walking up through each
+/// parent directory until one is found or the filesystem root is reached.
+fn discover_config<P: AsRef<Path>>(dir: P) -> Result<PathBuf> {
+    let mut searched: Vec<PathBuf> = Vec::new();
+    let mut current = Some(dir.as_ref().to_path_buf());
+
+    while let Some(dir) = current {
+	let candidate = dir.join("config.toml");
+	trace!("Checking for config at {}", candidate.display());
+	if candidate.exists() {
+	    debug!("Discovered configuration at {}", candidate.display());
+	    return Ok(candidate);
+	}
+
+	searched.push(dir.clone());
+	current = dir.parent().map(Path::to_path_buf);
+    }
+
+    let searched = searched.iter()
+	.map(|p| p.display().to_string())
+	.collect::<Vec<String>>()
+	.join("\n");
+    Err(anyhow!("no config.toml found; searched the following directories:\n{}", searched))
+}
+
 fn get_input<R: BufRead>(prompt: &str, reader: &mut R) -> Result<String> {
     let mut buf = String::new();
     println!("{}", prompt);
@@ -191,18 +274,66 @@ impl AppConfig {
 	    .with_context(|| format!("failed reading '{}' to string", &config.as_ref().display()))?;
 
 	trace!("Parsing configuration TOML");
-	let app_config: AppConfig = toml::from_str(&config_string)
+	let mut app_config: AppConfig = toml::from_str(&config_string)
             .context("failed to parse TOML")?;
 
+	debug!("Applying environment variable overrides");
+	app_config.apply_env_overrides()?;
+
 	Ok(app_config)
     }
 
-    pub(crate) fn generate<P: AsRef<Path>, R: BufRead>(dir: P, reader: &mut R) -> Result<AppConfig> {
+    /// Overlays environment variables onto an already-parsed `AppConfig`.
+    ///
+    /// Variables are matched by the `ARSE__` prefix, with each remaining
+    /// `__`-separated segment selecting a field of the config hierarchy, e.g.
+    /// `ARSE__SERVER__PORT` or `ARSE__SITE__AUTHOR`. Every offending variable
+    /// is collected into a single error rather than failing on the first.
+    fn apply_env_overrides(&mut self) -> Result<()> {
+	let mut errors: Vec<String> = Vec::new();
+
+	for (key, value) in std::env::vars() {
+	    let path = match key.strip_prefix(ENV_PREFIX) {
+		Some(path) => path,
+		None => continue,
+	    };
+
+	    trace!("Applying environment override: {}", &key);
+	    let segments: Vec<&str> = path.split("__").collect();
+	    if let Err(err) = self.set_env_field(&segments, &value) {
+		errors.push(format!("{}: {}", &key, err));
+	    }
+	}
+
+	if errors.is_empty() {
+	    Ok(())
+	} else {
+	    Err(anyhow!("invalid environment overrides:\n{}", errors.join("\n")))
+	}
+    }
+
+    fn set_env_field(&mut self, segments: &[&str], value: &str) -> Result<()> {
+	match segments {
+	    ["SERVER", "PORT"] => self.server.port = value.parse::<u16>()
+		.context("expected a u16")?,
+	    ["SERVER", "BIND"] => self.server.bind = value.to_owned(),
+	    ["SITE", "NAME"] => self.site.name = value.to_owned(),
+	    ["SITE", "AUTHOR"] => self.site.author = value.to_owned(),
+	    ["SITE", "TEMPLATE"] => self.site.template = value.to_owned(),
+	    ["DOCPATHS", "TEMPLATES"] => self.docpaths.templates = value.to_owned(),
+	    ["DOCPATHS", "WEBROOT"] => self.docpaths.webroot = value.to_owned(),
+	    other => return Err(anyhow!("unrecognized configuration key path '{}'", other.join("__"))),
+	}
+
+	Ok(())
+    }
+
+    pub(crate) fn generate<P: AsRef<Path>, R: BufRead>(dir: P, reader: &mut R, scaffold: Option<&str>) -> Result<AppConfig> {
 	info!("Generating new site configuration");
 	let docpaths = DocPaths::new(&dir);
 	let site = Site::new_from_input(reader)?;
 	let server = Server::new();
-	
+
 	let config = AppConfig {
 	    site,
 	    server,
@@ -213,6 +344,8 @@ impl AppConfig {
 	    .context("failed while creating site paths")?;
 	config.write(&dir)
 	    .context("failed to write site config to disk")?;
+	super::scaffold::expand(&config, scaffold)
+	    .context("failed to expand scaffold bundle")?;
 
 	Ok(config)
     }
@@ -240,11 +373,84 @@ impl AppConfig {
 	common::str_to_ro_file(&config, &conf_path)?;
 	Ok(())
     }
+
+    /// Validates the configuration and site tree without starting the
+    /// server, accumulating every problem found rather than stopping at the
+    /// first.
+    pub(crate) fn check(&self) -> Result<()> {
+	info!("Checking site configuration and tree");
+	let mut errors: Vec<String> = Vec::new();
+
+	if !Path::new(&self.docpaths.templates).exists() {
+	    errors.push(format!("templates path '{}' does not exist", &self.docpaths.templates));
+	}
+	if !Path::new(&self.docpaths.webroot).exists() {
+	    errors.push(format!("webroot path '{}' does not exist", &self.docpaths.webroot));
+	}
+
+	let mut known_dirs: HashSet<String> = HashSet::new();
+	known_dirs.insert("static".to_owned());
+	known_dirs.insert("main".to_owned());
+
+	for topic in &self.site.topics {
+	    let slug = common::slugify(topic);
+	    let posts = format!("{}/{}/posts", &self.docpaths.webroot, &slug);
+	    let ext = format!("{}/{}/ext", &self.docpaths.webroot, &slug);
+
+	    if !Path::new(&posts).exists() {
+		errors.push(format!("topic '{}' is missing posts directory '{}'", topic, &posts));
+	    }
+	    if !Path::new(&ext).exists() {
+		errors.push(format!("topic '{}' is missing ext directory '{}'", topic, &ext));
+	    }
+
+	    known_dirs.insert(slug);
+	}
+
+	if let Ok(entries) = std::fs::read_dir(&self.docpaths.webroot) {
+	    for entry in entries.filter_map(std::result::Result::ok) {
+		let path = entry.path();
+		if !path.is_dir() {
+		    continue;
+		}
+
+		if let Some(name) = entry.file_name().to_str() {
+		    if !known_dirs.contains(name) {
+			errors.push(format!("orphan webroot topic directory '{}' has no matching entry in site.topics", name));
+		    }
+		}
+	    }
+	}
+
+	let template_path = format!("{}/{}", &self.docpaths.templates, &self.site.template);
+	if !Path::new(&template_path).exists() {
+	    errors.push(format!("site template '{}' does not exist", &template_path));
+	}
+
+	if self.server.bind.parse::<std::net::IpAddr>().is_err() {
+	    errors.push(format!("server.bind '{}' is not a valid address", &self.server.bind));
+	}
+	if self.server.port == 0 {
+	    errors.push("server.port must be nonzero".to_owned());
+	}
+
+	if errors.is_empty() {
+	    Ok(())
+	} else {
+	    Err(anyhow!("site check failed:\n{}", errors.join("\n")))
+	}
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    /// `apply_env_overrides` reads the whole process environment, so tests
+    /// that set `ARSE__*` variables must not run concurrently with one
+    /// another.
+    static ENV_TEST_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn build_run_config() {
@@ -259,7 +465,7 @@ mod tests {
 	let dir = tempfile::tempdir().unwrap();
 	// Setup all target fields
 	let mut src: &[u8] = b"Site Name\nAuthor Name\nOne, Two, Three, And More\n";
-	let config = AppConfig::generate(&dir, &mut src);
+	let config = AppConfig::generate(&dir, &mut src, None);
 	assert!(config.is_ok());
 
 	let tmp_dir = &dir.path();
@@ -297,4 +503,97 @@ mod tests {
 	assert_eq!(reference_topics, csv_to_vec(&topics))
     }
 
+    #[test]
+    fn env_overrides_take_precedence_over_file() {
+	let _guard = ENV_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+	std::env::set_var("ARSE__SERVER__PORT", "4242");
+	std::env::set_var("ARSE__SITE__AUTHOR", "Env Author");
+
+	let config = AppConfig::from_path("./test_files/test-config.toml");
+
+	std::env::remove_var("ARSE__SERVER__PORT");
+	std::env::remove_var("ARSE__SITE__AUTHOR");
+
+	let config = config.unwrap();
+	assert_eq!(config.server.port, 4242);
+	assert_eq!(config.site.author, "Env Author");
+    }
+
+    #[test]
+    fn env_overrides_collect_all_parse_failures() {
+	let _guard = ENV_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+	std::env::set_var("ARSE__SERVER__PORT", "not-a-port");
+	std::env::set_var("ARSE__NOPE__NOPE", "irrelevant");
+
+	let config = AppConfig::from_path("./test_files/test-config.toml");
+
+	std::env::remove_var("ARSE__SERVER__PORT");
+	std::env::remove_var("ARSE__NOPE__NOPE");
+
+	let err = config.unwrap_err().to_string();
+	assert!(err.contains("ARSE__SERVER__PORT"));
+	assert!(err.contains("ARSE__NOPE__NOPE"));
+    }
+
+    #[test]
+    fn discover_config_walks_up_parent_directories() {
+	let dir = tempfile::tempdir().unwrap();
+	let config_path = dir.path().join("config.toml");
+	std::fs::write(&config_path, "").unwrap();
+
+	let nested = dir.path().join("a/b/c");
+	create_dir_all(&nested).unwrap();
+
+	let discovered = discover_config(&nested).unwrap();
+	assert_eq!(discovered, config_path);
+    }
+
+    #[test]
+    fn discover_config_reports_searched_directories_when_missing() {
+	let dir = tempfile::tempdir().unwrap();
+	let err = discover_config(dir.path()).unwrap_err().to_string();
+	assert!(err.contains(&dir.path().display().to_string()));
+    }
+
+    #[test]
+    fn check_passes_for_a_freshly_generated_site() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut src: &[u8] = b"Site Name\nAuthor Name\nOne, Two\n";
+	let config = AppConfig::generate(&dir, &mut src, None).unwrap();
+
+	std::fs::write(format!("{}/default.tmpl", &config.docpaths.templates), "").unwrap();
+
+	assert!(config.check().is_ok());
+    }
+
+    #[test]
+    fn check_reports_missing_topic_directory_template_and_bind() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut src: &[u8] = b"Site Name\nAuthor Name\nOne, Two\n";
+	let mut config = AppConfig::generate(&dir, &mut src, None).unwrap();
+
+	std::fs::remove_dir_all(format!("{}/two/posts", &config.docpaths.webroot)).unwrap();
+	config.server.bind = "not-an-address".to_owned();
+	config.server.port = 0;
+
+	let err = config.check().unwrap_err().to_string();
+	assert!(err.contains("two"));
+	assert!(err.contains("default.tmpl"));
+	assert!(err.contains("not-an-address"));
+	assert!(err.contains("nonzero"));
+    }
+
+    #[test]
+    fn check_reports_orphan_webroot_topic_directory() {
+	let dir = tempfile::tempdir().unwrap();
+	let mut src: &[u8] = b"Site Name\nAuthor Name\nOne\n";
+	let config = AppConfig::generate(&dir, &mut src, None).unwrap();
+	std::fs::write(format!("{}/default.tmpl", &config.docpaths.templates), "").unwrap();
+
+	create_dir_all(format!("{}/orphan/posts", &config.docpaths.webroot)).unwrap();
+
+	let err = config.check().unwrap_err().to_string();
+	assert!(err.contains("orphan"));
+    }
+
 }
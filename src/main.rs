@@ -11,19 +11,31 @@ copied, modified, or distributed except according to those terms.
 //! Main entry point for A Rust Site Engine.
 //!
 //! # Options
-//! - `run [config]`: Starts a server defined by the `[config]` TOML.
-//! - `new`: Creates a new `[config]` TOML from user input, and creates
-//!          the site's directory structure.
+//! - `run [config]`: Starts a server defined by the `[config]` TOML. If
+//!                   `[config]` is omitted, discovers `config.toml` by
+//!                   walking up from the current directory.
+//! - `new [--scaffold <dir>]`: Creates a new `[config]` TOML from user
+//!                             input, creates the site's directory
+//!                             structure, and expands a scaffold bundle of
+//!                             starter content. `--scaffold` points at a
+//!                             user bundle in place of the embedded default.
+//! - `build [config] [--out <dir>]`: Renders the site to static HTML and
+//!                                   copies its static assets into `<dir>`
+//!                                   (default `site/public`).
+//! - `check [config]`: Validates the configuration and site tree without
+//!                     starting the server.
 
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Error, Result};
 use log::{error, info};
 
+mod build;
 mod common;
 mod config;
 mod render;
 mod routes;
+mod scaffold;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -0,0 +1,196 @@
+/*
+A Rust Site Engine
+Copyright 2020-2024 Anthony Martinez
+
+Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+http://opensource.org/licenses/MIT>, at your option. This file may not be
+copied, modified, or distributed except according to those terms.
+*/
+
+//! Expands a bundle of template files into a new site's webroot.
+//!
+//! A bundle is a directory tree whose file names and contents may contain
+//! `{{ name }}`, `{{ author }}`, `{{ topic }}`, and `{{ slug }}` tokens.
+//! Entries whose path carries a `{{ topic }}` or `{{ slug }}` token are
+//! expanded once per topic declared in `site.topics`; every other entry is
+//! expanded once for the site as a whole.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, trace};
+
+use super::common;
+use super::config::AppConfig;
+use super::{Context, Result};
+
+/// The bundle shipped with the engine, used when `new` is run without
+/// `--scaffold`.
+const DEFAULT_BUNDLE: &[(&str, &str)] = &[
+    ("index.md", "# {{ name }}\n\nWelcome to {{ name }}, maintained by {{ author }}.\n"),
+    ("{{ slug }}/index.md", "# {{ topic }}\n\nPosts about {{ topic }} will appear here.\n"),
+];
+
+/// Expands `bundle` (or the embedded default, if `None`) into `config`'s
+/// webroot, substituting tokens from `config.site` and, for per-topic
+/// entries, from each declared topic in turn.
+pub(crate) fn expand<P: AsRef<Path>>(config: &AppConfig, bundle: Option<P>) -> Result<()> {
+    info!("Expanding scaffold bundle into site webroot");
+    let entries = match bundle {
+        Some(dir) => load_bundle(dir.as_ref())
+            .with_context(|| format!("failed loading scaffold bundle '{}'", dir.as_ref().display()))?,
+        None => default_bundle(),
+    };
+
+    for (rel_path, content) in &entries {
+        let rel_path = rel_path.to_string_lossy();
+
+        if rel_path.contains("{{ topic }}") || rel_path.contains("{{ slug }}") {
+            for topic in &config.site.topics {
+                let slug = common::slugify(topic);
+                write_entry(config, &rel_path, content, Some(topic), Some(&slug))?;
+            }
+        } else {
+            write_entry(config, &rel_path, content, None, None)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_entry(
+    config: &AppConfig,
+    rel_path: &str,
+    content: &str,
+    topic: Option<&str>,
+    slug: Option<&str>,
+) -> Result<()> {
+    let out_rel = substitute(rel_path, config, topic, slug);
+    let out_content = substitute(content, config, topic, slug);
+
+    let dest = Path::new(&config.docpaths.webroot).join(&out_rel);
+    trace!("Writing scaffolded file: {}", dest.display());
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create '{}'", parent.display()))?;
+    }
+    common::str_to_ro_file(&out_content, &dest)?;
+
+    Ok(())
+}
+
+/// Replaces every recognized token in `input` with values drawn from
+/// `config.site`, and from `topic`/`slug` when expanding a per-topic entry.
+fn substitute(input: &str, config: &AppConfig, topic: Option<&str>, slug: Option<&str>) -> String {
+    let mut out = input
+        .replace("{{ name }}", &config.site.name)
+        .replace("{{ author }}", &config.site.author);
+
+    if let Some(topic) = topic {
+        out = out.replace("{{ topic }}", topic);
+    }
+    if let Some(slug) = slug {
+        out = out.replace("{{ slug }}", slug);
+    }
+
+    out
+}
+
+fn default_bundle() -> Vec<(PathBuf, String)> {
+    DEFAULT_BUNDLE
+        .iter()
+        .map(|(path, content)| (PathBuf::from(path), (*content).to_owned()))
+        .collect()
+}
+
+fn load_bundle(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    debug!("Loading user scaffold bundle from {}", dir.display());
+    let mut entries = Vec::new();
+
+    for path in walk_files(dir)? {
+        let rel = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed reading '{}'", path.display()))?;
+        entries.push((rel, content));
+    }
+
+    Ok(entries)
+}
+
+fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let read_dir = fs::read_dir(dir)
+        .with_context(|| format!("failed reading bundle directory '{}'", dir.display()))?;
+    for entry in read_dir {
+        let entry = entry.with_context(|| format!("failed reading entry in '{}'", dir.display()))?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            files.extend(walk_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DocPaths, Server, Site};
+
+    fn test_config(webroot: &Path, topics: Vec<&str>) -> AppConfig {
+        AppConfig {
+            site: Site {
+                name: "Test Site".to_owned(),
+                author: "Test Author".to_owned(),
+                template: "default.tmpl".to_owned(),
+                topics: topics.into_iter().map(str::to_owned).collect(),
+            },
+            server: Server::new(),
+            docpaths: DocPaths {
+                templates: webroot.join("templates").display().to_string(),
+                webroot: webroot.join("webroot").display().to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn default_bundle_expands_site_and_topic_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path(), vec!["And More"]);
+
+        expand::<&Path>(&config, None).unwrap();
+
+        let site_index = Path::new(&config.docpaths.webroot).join("index.md");
+        let topic_index = Path::new(&config.docpaths.webroot).join("and-more/index.md");
+
+        let site_content = fs::read_to_string(&site_index).unwrap();
+        assert!(site_content.contains("Test Site"));
+        assert!(site_content.contains("Test Author"));
+
+        let topic_content = fs::read_to_string(&topic_index).unwrap();
+        assert!(topic_content.contains("And More"));
+    }
+
+    #[test]
+    fn user_bundle_substitutes_tokens_in_path_and_body() {
+        let bundle_dir = tempfile::tempdir().unwrap();
+        let nested = bundle_dir.path().join("{{ slug }}");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("index.md"), "# {{ topic }}\n").unwrap();
+
+        let site_dir = tempfile::tempdir().unwrap();
+        let config = test_config(site_dir.path(), vec!["One", "Two"]);
+
+        expand(&config, Some(bundle_dir.path())).unwrap();
+
+        let one_index = Path::new(&config.docpaths.webroot).join("one/index.md");
+        let two_index = Path::new(&config.docpaths.webroot).join("two/index.md");
+        assert_eq!(fs::read_to_string(&one_index).unwrap(), "# One\n");
+        assert_eq!(fs::read_to_string(&two_index).unwrap(), "# Two\n");
+    }
+}